@@ -1,10 +1,10 @@
-use std::fs::File;
 use std::error::Error;
-use std::io::prelude::*;
+use std::path::Path;
 use std::collections::HashMap;
 
 use log::warn;
 use clang::*;
+use cargo_toml::Manifest;
 
 pub use clang::TypeKind;
 
@@ -15,6 +15,11 @@ pub struct FinchType {
   pub pointee_type: Option<Box<FinchType>>,
   pub canonical_type: Option<Box<FinchType>>,
   pub sizeof: Option<usize>,
+  // Name of the `FinchClass` this type is the opaque pointer for, if any.
+  // Filled in by `resolve_cross_references` once every class in the crate
+  // has been collected, since a method can return a class that hasn't been
+  // seen yet at the point its own type is parsed.
+  pub finch_class: Option<String>,
 }
 
 impl<'tu> From<Type<'tu>> for FinchType {
@@ -35,10 +40,148 @@ impl<'tu> From<Type<'tu>> for FinchType {
         }
       },
       sizeof: value.get_sizeof().ok(),
+      finch_class: None,
     }
   }
 }
 
+impl FinchType {
+  // The type with typedefs resolved away, i.e. `canonical_type` when one
+  // was recorded, or `self` when `self` already *is* its own canonical form
+  // (the common case for a plain, non-typedef'd struct/pointer type).
+  fn canonical(&self) -> &FinchType {
+    self.canonical_type.as_deref().unwrap_or(self)
+  }
+
+  fn resolve_finch_class(&mut self, classes_by_c_name: &HashMap<String, String>) {
+    if let Some(canonical_type) = &mut self.canonical_type {
+      canonical_type.resolve_finch_class(classes_by_c_name);
+    }
+
+    if let Some(pointee_type) = &mut self.pointee_type {
+      pointee_type.resolve_finch_class(classes_by_c_name);
+    }
+
+    if self.finch_class.is_none() {
+      // A class handle is always passed as a pointer, so look past the
+      // pointer to what it points at rather than matching the pointer type
+      // itself (whose display name carries a trailing ` *`).
+      let pointee = self.canonical().pointee_type.as_deref().map(FinchType::canonical);
+      if let Some(pointee) = pointee {
+        self.finch_class = classes_by_c_name.get(&pointee.display_name).cloned();
+      }
+    }
+  }
+}
+
+// Where a `FinchDiagnostic` was raised, so a user can jump straight to the
+// offending declaration in the header that produced it.
+#[derive(Clone, Debug)]
+pub struct FinchLocation {
+  pub file: Option<String>,
+  pub line: u32,
+  pub column: u32,
+}
+
+impl FinchLocation {
+  fn from_entity(e: &Entity) -> Option<Self> {
+    let (file, line, column, _offset) = e.get_location()?.get_spelling_location();
+    Some(Self {
+      file: file.map(|f| f.get_path().to_string_lossy().into_owned()),
+      line,
+      column,
+    })
+  }
+}
+
+#[derive(Clone, Debug)]
+pub enum FinchErrorKind {
+  MissingClass(String),
+  MalformedIdentifier(String),
+  ArgumentCountMismatch { expected: usize, found: usize },
+  UnknownIdentifier(String),
+  NamespaceMismatch { expected: String, found: String },
+  MissingNamespace,
+}
+
+impl std::fmt::Display for FinchErrorKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      FinchErrorKind::MissingClass(name) => write!(f, "no class named '{}' has been seen yet", name),
+      FinchErrorKind::MalformedIdentifier(reason) => write!(f, "malformed finch identifier: {}", reason),
+      FinchErrorKind::ArgumentCountMismatch { expected, found } => write!(f, "expected at least {} argument(s), found {}", expected, found),
+      FinchErrorKind::UnknownIdentifier(name) => write!(f, "unknown identifier '{}'", name),
+      FinchErrorKind::NamespaceMismatch { expected, found } => write!(f, "namespace mismatch, expected '{}', got '{}'", expected, found),
+      FinchErrorKind::MissingNamespace => write!(f, "finch identifier found before the crate namespace was entered"),
+    }
+  }
+}
+
+// A non-fatal problem encountered while walking the expanded header. These
+// are collected onto `FinchOutput` instead of aborting the whole parse, so a
+// caller can see exactly which declarations couldn't be handled.
+#[derive(Clone, Debug)]
+pub struct FinchDiagnostic {
+  pub kind: FinchErrorKind,
+  pub location: Option<FinchLocation>,
+}
+
+impl FinchDiagnostic {
+  fn new(kind: FinchErrorKind, e: &Entity) -> Self {
+    Self {
+      kind,
+      location: FinchLocation::from_entity(e),
+    }
+  }
+}
+
+impl std::fmt::Display for FinchDiagnostic {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match &self.location {
+      Some(loc) => write!(f, "{}:{}:{}: {}", loc.file.as_deref().unwrap_or("<unknown>"), loc.line, loc.column, self.kind),
+      None => write!(f, "{}", self.kind),
+    }
+  }
+}
+
+// Collects the name/type of every argument of `e`, skipping the first
+// `skip` of them (used to drop the receiver off of instance methods).
+fn collect_args(c_fn_name: &str, e: &Entity, skip: usize) -> Result<(Vec<String>, Vec<FinchType>), FinchDiagnostic> {
+  let args = e.get_arguments()
+    .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("'{}' has no argument list", c_fn_name)), e))?;
+
+  if args.len() < skip {
+    return Err(FinchDiagnostic::new(FinchErrorKind::ArgumentCountMismatch { expected: skip, found: args.len() }, e));
+  }
+
+  let mut arg_names = Vec::with_capacity(args.len() - skip);
+  let mut arg_types = Vec::with_capacity(args.len() - skip);
+  for arg in args.into_iter().skip(skip) {
+    let arg_name = arg.get_display_name()
+      .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("unnamed argument on '{}'", c_fn_name)), &arg))?;
+    let arg_type = arg.get_type()
+      .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("untyped argument on '{}'", c_fn_name)), &arg))?;
+    arg_names.push(arg_name);
+    arg_types.push(FinchType::from(arg_type));
+  }
+
+  Ok((arg_names, arg_types))
+}
+
+// `parts[idx]`, but as a diagnostic instead of a panic for tags that start
+// with `___finch_bindgen` yet don't have enough `___`-separated segments to
+// match any known scheme (a truncated or future tag, say).
+fn get_part<'a>(parts: &[&'a str], idx: usize, identifier: &str, e: &Entity) -> Result<&'a str, FinchDiagnostic> {
+  parts.get(idx).copied()
+    .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("'{}' has too few '___'-separated segments", identifier)), e))
+}
+
+fn get_result_type(c_fn_name: &str, e: &Entity) -> Result<FinchType, FinchDiagnostic> {
+  e.get_result_type()
+    .map(FinchType::from)
+    .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("'{}' has no return type", c_fn_name)), e))
+}
+
 #[derive(Clone, Debug)]
 pub struct FinchNew {
   pub class_name: String,
@@ -50,22 +193,17 @@ pub struct FinchNew {
 }
 
 impl FinchNew {
-  fn new(class_name: String, fn_name: String, c_fn_name: String, e: Entity) -> Self {
-    let mut arg_names = Vec::new();
-    let mut arg_types = Vec::new();
-    for arg in e.get_arguments().unwrap() {
-      arg_names.push(arg.get_display_name().unwrap());
-      arg_types.push(FinchType::from(arg.get_type().unwrap()));
-    }
+  fn new(class_name: String, fn_name: String, c_fn_name: String, e: Entity) -> Result<Self, FinchDiagnostic> {
+    let (arg_names, arg_types) = collect_args(&c_fn_name, &e, 0)?;
 
-    Self {
+    Ok(Self {
       class_name,
       fn_name,
       c_fn_name,
       arg_names,
       arg_types,
       comments: e.get_comment(),
-    }
+    })
   }
 }
 
@@ -90,28 +228,21 @@ pub struct FinchMethod {
 }
 
 impl FinchMethod {
-  fn new(class_name: String, method_name: String, fn_name: String, c_fn_name: String, consume: bool, e: Entity) -> Self {
-    let mut arg_names = Vec::new();
-    let mut arg_types = Vec::new();
-
-    let mut args = e.get_arguments().unwrap();
-    args.remove(0);
-    for arg in args {
-      arg_names.push(arg.get_display_name().unwrap());
-      arg_types.push(FinchType::from(arg.get_type().unwrap()));
-    }
+  fn new(class_name: String, method_name: String, fn_name: String, c_fn_name: String, consume: bool, e: Entity) -> Result<Self, FinchDiagnostic> {
+    let (arg_names, arg_types) = collect_args(&c_fn_name, &e, 1)?;
+    let ret_type = get_result_type(&c_fn_name, &e)?;
 
-    Self {
+    Ok(Self {
       class_name,
       method_name,
       fn_name,
       c_fn_name,
-      ret_type: FinchType::from(e.get_result_type().unwrap()),
+      ret_type,
       arg_names,
       arg_types,
       comments: e.get_comment(),
       consume,
-    }
+    })
   }
 }
 
@@ -128,24 +259,20 @@ pub struct FinchStatic {
 }
 
 impl FinchStatic {
-  fn new(class_name: String, method_name: String, fn_name: String, c_fn_name: String, e: Entity) -> Self {
-    let mut arg_names = Vec::new();
-    let mut arg_types = Vec::new();
-    for arg in e.get_arguments().unwrap() {
-      arg_names.push(arg.get_display_name().unwrap());
-      arg_types.push(FinchType::from(arg.get_type().unwrap()));
-    }
+  fn new(class_name: String, method_name: String, fn_name: String, c_fn_name: String, e: Entity) -> Result<Self, FinchDiagnostic> {
+    let (arg_names, arg_types) = collect_args(&c_fn_name, &e, 0)?;
+    let ret_type = get_result_type(&c_fn_name, &e)?;
 
-    Self {
+    Ok(Self {
       class_name,
       method_name,
       fn_name,
       c_fn_name,
-      ret_type: FinchType::from(e.get_result_type().unwrap()),
+      ret_type,
       arg_names,
       arg_types,
       comments: e.get_comment(),
-    }
+    })
   }
 }
 
@@ -160,15 +287,17 @@ pub struct FinchGetter {
 }
 
 impl FinchGetter {
-  fn new(class_name: String, field_name: String, fn_name: String, c_fn_name: String, e: Entity) -> Self {
-    Self {
+  fn new(class_name: String, field_name: String, fn_name: String, c_fn_name: String, e: Entity) -> Result<Self, FinchDiagnostic> {
+    let type_ = get_result_type(&c_fn_name, &e)?;
+
+    Ok(Self {
       class_name,
       field_name,
       fn_name,
       c_fn_name,
-      type_: FinchType::from(e.get_result_type().unwrap()),
+      type_,
       comments: e.get_comment(),
-    }
+    })
   }
 }
 
@@ -183,15 +312,127 @@ pub struct FinchSetter {
 }
 
 impl FinchSetter {
-  fn new(class_name: String, field_name: String, fn_name: String, c_fn_name: String, e: Entity) -> Self {
-    Self {
+  fn new(class_name: String, field_name: String, fn_name: String, c_fn_name: String, e: Entity) -> Result<Self, FinchDiagnostic> {
+    let args = e.get_arguments()
+      .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("'{}' has no argument list", c_fn_name)), &e))?;
+    let value_arg = args.get(1)
+      .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::ArgumentCountMismatch { expected: 2, found: args.len() }, &e))?;
+    let value_type = value_arg.get_type()
+      .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("untyped argument on '{}'", c_fn_name)), value_arg))?;
+
+    Ok(Self {
       class_name,
       field_name,
       fn_name,
       c_fn_name,
-      type_: FinchType::from(e.get_arguments().unwrap()[1].get_type().unwrap()),
+      type_: FinchType::from(value_type),
       comments: e.get_comment(),
+    })
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct FinchEnumVariant {
+  pub name: String,
+  pub value: i64,
+  pub comments: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FinchEnum {
+  pub name: String,
+  pub c_name: String,
+  pub repr: FinchType,
+  pub variants: Vec<FinchEnumVariant>,
+  pub comments: Option<String>,
+}
+
+impl FinchEnum {
+  fn new(name: String, c_name: String, e: Entity) -> Result<Self, FinchDiagnostic> {
+    let underlying = e.get_typedef_underlying_type()
+      .and_then(|ty| ty.get_declaration())
+      .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("could not resolve underlying enum for '{}'", name)), &e))?;
+
+    let repr = underlying.get_enum_underlying_type()
+      .map(FinchType::from)
+      .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("'{}' does not name an enum declaration", name)), &underlying))?;
+
+    let mut variants = Vec::new();
+    for child in underlying.get_children() {
+      if child.get_kind() != EntityKind::EnumConstantDecl {
+        continue;
+      }
+
+      let (value, _) = child.get_enum_constant_value()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("enum constant on '{}' has no value", name)), &child))?;
+      let variant_name = child.get_name()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("unnamed enum constant on '{}'", name)), &child))?;
+
+      variants.push(FinchEnumVariant {
+        name: variant_name,
+        value,
+        comments: child.get_comment(),
+      });
     }
+
+    Ok(Self {
+      name,
+      c_name,
+      repr,
+      variants,
+      comments: e.get_comment(),
+    })
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct FinchConst {
+  pub name: String,
+  pub c_name: String,
+  pub type_: FinchType,
+  pub comments: Option<String>,
+}
+
+impl FinchConst {
+  fn new(name: String, c_name: String, e: Entity) -> Result<Self, FinchDiagnostic> {
+    let type_ = e.get_type()
+      .map(FinchType::from)
+      .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier(format!("'{}' has no type", name)), &e))?;
+
+    Ok(Self {
+      name,
+      c_name,
+      type_,
+      comments: e.get_comment(),
+    })
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct FinchFunction {
+  pub name: String,
+  pub fn_name: String,
+  pub c_fn_name: String,
+  pub ret_type: FinchType,
+  pub arg_names: Vec<String>,
+  pub arg_types: Vec<FinchType>,
+  pub comments: Option<String>,
+}
+
+impl FinchFunction {
+  fn new(name: String, fn_name: String, c_fn_name: String, e: Entity) -> Result<Self, FinchDiagnostic> {
+    let (arg_names, arg_types) = collect_args(&c_fn_name, &e, 0)?;
+    let ret_type = get_result_type(&c_fn_name, &e)?;
+
+    Ok(Self {
+      name,
+      fn_name,
+      c_fn_name,
+      ret_type,
+      arg_names,
+      arg_types,
+      comments: e.get_comment(),
+    })
   }
 }
 
@@ -200,6 +441,7 @@ pub struct FinchClass {
   pub name: String,
   pub c_name: String,
   pub comments: Option<String>,
+  pub bases: Vec<String>,
   pub new: Option<FinchNew>,
   pub drop: Option<FinchDrop>,
   pub statics: Vec<FinchStatic>,
@@ -210,10 +452,24 @@ pub struct FinchClass {
 
 impl FinchClass {
   fn new(name: String, c_name: String, e: Entity) -> Self {
+    // The typedef only names the class; its base specifiers live on the
+    // record declaration the typedef's underlying type points at.
+    let bases = e.get_typedef_underlying_type()
+      .and_then(|ty| ty.get_declaration())
+      .map(|decl| {
+        decl.get_children().into_iter()
+          .filter(|child| child.get_kind() == EntityKind::BaseSpecifier)
+          .filter_map(|child| child.get_type())
+          .map(|ty| ty.get_display_name())
+          .collect()
+      })
+      .unwrap_or_default();
+
     Self {
       name,
       c_name,
       comments: e.get_comment(),
+      bases,
       new: None,
       drop: None,
       statics: Vec::new(),
@@ -228,35 +484,42 @@ struct ParserState {
   in_finch: bool,
   in_internal: bool,
   namespace: Option<String>,
-  classes: HashMap<String, FinchClass>
+  classes: HashMap<String, FinchClass>,
+  enums: HashMap<String, FinchEnum>,
+  consts: HashMap<String, FinchConst>,
+  functions: HashMap<String, FinchFunction>,
+  diagnostics: Vec<FinchDiagnostic>,
 }
 
 fn process_children(state: &mut ParserState, e: Entity) {
   for child in e.get_children() {
-    process_entity(state, child);
+    if let Err(diagnostic) = process_entity(state, child) {
+      state.diagnostics.push(diagnostic);
+    }
   }
 }
 
-fn process_entity(state: &mut ParserState, e: Entity) {
+fn process_entity(state: &mut ParserState, e: Entity) -> Result<(), FinchDiagnostic> {
   match e.get_kind() {
     EntityKind::TranslationUnit => {
-      for child in e.get_children() {
-        process_entity(state, child);
-      }
+      process_children(state, e);
     }
 
     EntityKind::Namespace => {
-      if !state.in_finch && e.get_display_name().unwrap() == "finch" {
+      let name = e.get_display_name()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier("namespace has no name".to_string()), &e))?;
+
+      if !state.in_finch && name == "finch" {
         state.in_finch = true;
         process_children(state, e);
-      } else if !state.in_internal && e.get_display_name().unwrap() == "bindgen" {
+      } else if !state.in_internal && name == "bindgen" {
         state.in_internal = true;
         process_children(state, e);
       } else if state.in_finch && state.in_internal && state.namespace.is_none() {
-        state.namespace = Some(e.get_display_name().unwrap());
+        state.namespace = Some(name);
         process_children(state, e);
       } else if state.in_finch {
-        warn!("unknown namespace found '{}'", e.get_display_name().unwrap());
+        warn!("unknown namespace found '{}'", name);
       }
     }
 
@@ -268,63 +531,79 @@ fn process_entity(state: &mut ParserState, e: Entity) {
 
     EntityKind::TypeAliasDecl => {
       if !state.in_finch || !state.in_internal {
-        return;
+        return Ok(());
       }
 
-      let ty_name = e.get_name().unwrap();
+      let ty_name = e.get_name()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier("type alias has no name".to_string()), &e))?;
       if !ty_name.as_str().starts_with("___finch_bindgen") {
         warn!("unknown identifier found '{}'", ty_name);
-        return;
+        return Ok(());
       }
 
       let parts: Vec<&str> = ty_name.as_str().split("___").collect();
-      if parts[2] != state.namespace.as_ref().unwrap() {
-        warn!("namespace mismatch, expected '{}', got '{}'", state.namespace.as_ref().unwrap(), parts[3]);
-        return;
+      let namespace = state.namespace.clone()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MissingNamespace, &e))?;
+      let found_namespace = get_part(&parts, 2, &ty_name, &e)?;
+      if found_namespace != namespace {
+        return Err(FinchDiagnostic::new(FinchErrorKind::NamespaceMismatch { expected: namespace, found: found_namespace.to_string() }, &e));
       }
 
-      if parts[3] != "class" {
-        warn!("unknown identifier found '{}'", parts[3]);
-        return;
-      }
+      let display_name = e.get_display_name()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier("type alias has no display name".to_string()), &e))?;
+      let c_name = format!("finch::bindgen::{}::{}", namespace, display_name);
+
+      match get_part(&parts, 3, &ty_name, &e)? {
+        "class" => {
+          let class_name = get_part(&parts, 4, &ty_name, &e)?.to_string();
+          if !state.classes.contains_key(&class_name) {
+            state.classes.insert(class_name.clone(), FinchClass::new(class_name, c_name, e));
+          }
+        }
 
-      let class_name = parts[4].to_string();
-      let _class = state.classes
-        .entry(class_name.clone())
-        .or_insert(
-          FinchClass::new(
-            class_name.clone(), 
-            format!("finch::bindgen::{}::{}", state.namespace.as_ref().unwrap(), e.get_display_name().unwrap()), 
-            e,
-          ),
-        );
+        "enum" => {
+          let enum_name = get_part(&parts, 4, &ty_name, &e)?.to_string();
+          if !state.enums.contains_key(&enum_name) {
+            let finch_enum = FinchEnum::new(enum_name.clone(), c_name, e)?;
+            state.enums.insert(enum_name, finch_enum);
+          }
+        }
+
+        x => {
+          return Err(FinchDiagnostic::new(FinchErrorKind::UnknownIdentifier(x.to_string()), &e));
+        },
+      }
     }
 
     EntityKind::FunctionDecl => {
       if !state.in_finch || !state.in_internal {
-        return;
+        return Ok(());
       }
 
-      let c_fn_name = e.get_name().unwrap();
+      let c_fn_name = e.get_name()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier("function has no name".to_string()), &e))?;
       if !c_fn_name.as_str().starts_with("___finch_bindgen") {
         warn!("unknown identifier found '{}'", c_fn_name);
-        return;
+        return Ok(());
       }
 
       let parts: Vec<&str> = c_fn_name.as_str().split("___").collect();
-      if parts[2] != state.namespace.as_ref().unwrap() {
-        warn!("namespace mismatch, expected '{}', got '{}'", state.namespace.as_ref().unwrap(), parts[3]);
-        return;
+      let namespace = state.namespace.clone()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MissingNamespace, &e))?;
+      let found_namespace = get_part(&parts, 2, &c_fn_name, &e)?;
+      if found_namespace != namespace {
+        return Err(FinchDiagnostic::new(FinchErrorKind::NamespaceMismatch { expected: namespace, found: found_namespace.to_string() }, &e));
       }
 
-      match parts[3] {
+      match get_part(&parts, 3, &c_fn_name, &e)? {
         "class" => {
-          let fn_name = format!("finch::bindgen::{}::{}", state.namespace.as_ref().unwrap(), c_fn_name);
+          let fn_name = format!("finch::bindgen::{}::{}", namespace, c_fn_name);
 
-          let class_name = parts[4].to_string();
-          let class = state.classes.get_mut(&class_name).expect(format!("failed to find class '{}'", class_name).as_str());
+          let class_name = get_part(&parts, 4, &c_fn_name, &e)?.to_string();
+          let class = state.classes.get_mut(&class_name)
+            .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MissingClass(class_name.clone()), &e))?;
 
-          match parts[5] {
+          match get_part(&parts, 5, &c_fn_name, &e)? {
             "drop" => {
               class.drop = Some(FinchDrop {
                 class_name,
@@ -334,45 +613,98 @@ fn process_entity(state: &mut ParserState, e: Entity) {
             },
 
             "method" => {
-              class.methods.push(FinchMethod::new(class_name, parts[6].to_string(), fn_name.to_string(), c_fn_name.to_string(), false, e));
+              let method_name = get_part(&parts, 6, &c_fn_name, &e)?.to_string();
+              class.methods.push(FinchMethod::new(class_name, method_name, fn_name.to_string(), c_fn_name.to_string(), false, e)?);
             }
 
             "method_consume" => {
-              class.methods.push(FinchMethod::new(class_name, parts[6].to_string(), fn_name.to_string(), c_fn_name.to_string(), true, e));
+              let method_name = get_part(&parts, 6, &c_fn_name, &e)?.to_string();
+              class.methods.push(FinchMethod::new(class_name, method_name, fn_name.to_string(), c_fn_name.to_string(), true, e)?);
             }
-            
+
             "static" => {
-              if parts[6] == "new" {
-                class.new = Some(FinchNew::new(class_name, fn_name.to_string(), c_fn_name.to_string(), e));
+              let static_name = get_part(&parts, 6, &c_fn_name, &e)?;
+              if static_name == "new" {
+                class.new = Some(FinchNew::new(class_name, fn_name.to_string(), c_fn_name.to_string(), e)?);
               } else {
-                class.statics.push(FinchStatic::new(class_name, parts[6].to_string(), fn_name.to_string(), c_fn_name.to_string(), e));
+                class.statics.push(FinchStatic::new(class_name, static_name.to_string(), fn_name.to_string(), c_fn_name.to_string(), e)?);
               }
             },
 
             "getter" => {
-              class.getters.push(FinchGetter::new(class_name, parts[6].to_string(), fn_name.to_string(), c_fn_name.to_string(), e));
+              let field_name = get_part(&parts, 6, &c_fn_name, &e)?.to_string();
+              class.getters.push(FinchGetter::new(class_name, field_name, fn_name.to_string(), c_fn_name.to_string(), e)?);
             }
 
             "setter" => {
-              class.setters.push(FinchSetter::new(class_name, parts[6].to_string(), fn_name.to_string(), c_fn_name.to_string(), e));
+              let field_name = get_part(&parts, 6, &c_fn_name, &e)?.to_string();
+              class.setters.push(FinchSetter::new(class_name, field_name, fn_name.to_string(), c_fn_name.to_string(), e)?);
             }
 
             x => {
-              warn!("unknown identifier found '{}'", x)
+              return Err(FinchDiagnostic::new(FinchErrorKind::UnknownIdentifier(x.to_string()), &e));
             },
           }
         },
 
+        "fn" => {
+          let fn_name = format!("finch::bindgen::{}::{}", namespace, c_fn_name);
+          let name = get_part(&parts, 4, &c_fn_name, &e)?.to_string();
+          let finch_function = FinchFunction::new(name.clone(), fn_name, c_fn_name.to_string(), e)?;
+          if !state.functions.contains_key(&name) {
+            state.functions.insert(name, finch_function);
+          }
+        }
+
         x => {
-          warn!("unknown identifier found '{}'", x)
+          return Err(FinchDiagnostic::new(FinchErrorKind::UnknownIdentifier(x.to_string()), &e));
         },
       }
+    }
+
+    EntityKind::VarDecl => {
+      if !state.in_finch || !state.in_internal {
+        return Ok(());
+      }
+
+      let var_name = e.get_name()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier("variable has no name".to_string()), &e))?;
+      if !var_name.as_str().starts_with("___finch_bindgen") {
+        warn!("unknown identifier found '{}'", var_name);
+        return Ok(());
+      }
 
-      println!("{:?}", parts);
+      let parts: Vec<&str> = var_name.as_str().split("___").collect();
+      let namespace = state.namespace.clone()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MissingNamespace, &e))?;
+      let found_namespace = get_part(&parts, 2, &var_name, &e)?;
+      if found_namespace != namespace {
+        return Err(FinchDiagnostic::new(FinchErrorKind::NamespaceMismatch { expected: namespace, found: found_namespace.to_string() }, &e));
+      }
+
+      let display_name = e.get_display_name()
+        .ok_or_else(|| FinchDiagnostic::new(FinchErrorKind::MalformedIdentifier("variable has no display name".to_string()), &e))?;
+      let c_name = format!("finch::bindgen::{}::{}", namespace, display_name);
+
+      match get_part(&parts, 3, &var_name, &e)? {
+        "const" => {
+          let const_name = get_part(&parts, 4, &var_name, &e)?.to_string();
+          if !state.consts.contains_key(&const_name) {
+            let finch_const = FinchConst::new(const_name.clone(), c_name, e)?;
+            state.consts.insert(const_name, finch_const);
+          }
+        }
+
+        x => {
+          return Err(FinchDiagnostic::new(FinchErrorKind::UnknownIdentifier(x.to_string()), &e));
+        },
+      }
     }
 
     _ => {},
   }
+
+  Ok(())
 }
 
 #[derive(Clone, Debug)]
@@ -397,35 +729,103 @@ impl Error for FinchError {
 #[derive(Clone, Debug)]
 pub struct FinchOutput {
   pub classes: HashMap<String, FinchClass>,
+  pub enums: HashMap<String, FinchEnum>,
+  pub consts: HashMap<String, FinchConst>,
+  pub functions: HashMap<String, FinchFunction>,
+  pub diagnostics: Vec<FinchDiagnostic>,
+}
+
+fn get_package_name_from_manifest(manifest: &Manifest) -> Result<String, Box<dyn Error>> {
+  let package = manifest.package.as_ref().ok_or(Box::new(FinchError("Cargo.toml does not have [package] table")))?;
+  Ok(package.name.clone())
 }
 
 fn get_package_name_from_cargo_toml() -> Result<String, Box<dyn Error>> {
-  let mut cargo_toml_file = File::open("Cargo.toml")?;
-  let mut cargo_toml = String::new();
-  cargo_toml_file.read_to_string(&mut cargo_toml)?;
-  let cargo_toml = cargo_toml.parse::<toml::Value>()?;
-
-  let cargo_table;
-  if let toml::Value::Table(table) = cargo_toml {
-    cargo_table = table;
-  } else {
-    return Err(Box::new(FinchError("Cargo.toml does not have root table element")));
+  let manifest = Manifest::from_path("Cargo.toml")?;
+  get_package_name_from_manifest(&manifest)
+}
+
+// Workspace members don't list their own manifest path, so we look for
+// `Cargo.toml` alongside whatever else lives in the member directory
+// rather than assuming it's there.
+fn find_member_cargo_toml(member_dir: &Path) -> Result<std::path::PathBuf, Box<dyn Error>> {
+  for entry in std::fs::read_dir(member_dir)? {
+    let entry = entry?;
+    if entry.file_name() == "Cargo.toml" {
+      return Ok(entry.path());
+    }
   }
 
-  let package_value = cargo_table.get("package").ok_or(Box::new(FinchError("Cargo.toml does not have [package] table")))?;
-  let package;
-  if let toml::Value::Table(package_table) = package_value {
-    package = package_table;
-  } else {
-    return Err(Box::new(FinchError("Cargo.toml does not have [package] table")));
+  Err(Box::new(FinchError("workspace member is missing a Cargo.toml")))
+}
+
+// `name` matched against a glob `pattern` with at most one `*`, which covers
+// cargo's common workspace member glob (`crates/*`) without pulling in a
+// whole glob crate for one wildcard.
+fn glob_match(pattern: &str, name: &str) -> bool {
+  match pattern.split_once('*') {
+    Some((prefix, suffix)) => name.len() >= prefix.len() + suffix.len() && name.starts_with(prefix) && name.ends_with(suffix),
+    None => pattern == name,
   }
+}
 
-  let name_value = package.get("name").ok_or(Box::new(FinchError("Cargo.toml does not have package name string")))?;
-  if let toml::Value::String(name) = name_value {
-    Ok(name.to_string())
-  } else {
-    Err(Box::new(FinchError("Cargo.toml does not have package name string")))
+// `[workspace].members` entries are allowed to be globs (`crates/*`), which
+// `cargo_toml` does not expand for us. Expand the last path component as a
+// glob against its parent directory; a plain, glob-free member just passes
+// through unchanged.
+fn expand_member_dirs(member: &str) -> Vec<std::path::PathBuf> {
+  let member_path = Path::new(member);
+  if !member.contains('*') {
+    return vec![member_path.to_path_buf()];
+  }
+
+  let pattern = match member_path.file_name().and_then(|name| name.to_str()) {
+    Some(pattern) => pattern,
+    None => return Vec::new(),
+  };
+  let parent = member_path.parent().unwrap_or_else(|| Path::new("."));
+
+  let mut dirs = Vec::new();
+  if let Ok(entries) = std::fs::read_dir(parent) {
+    for entry in entries.flatten() {
+      if entry.path().is_dir() && glob_match(pattern, &entry.file_name().to_string_lossy()) {
+        dirs.push(entry.path());
+      }
+    }
   }
+
+  dirs
+}
+
+// A crate `generate` should run the cbindgen + clang pipeline over, and the
+// directory cbindgen should treat as its crate root.
+struct CrateTarget {
+  name: String,
+  dir: std::path::PathBuf,
+}
+
+fn get_workspace_member_targets(manifest: &Manifest) -> Result<Vec<CrateTarget>, Box<dyn Error>> {
+  let workspace = manifest.workspace.as_ref().ok_or(Box::new(FinchError("Cargo.toml does not have a [package] or [workspace] table")))?;
+
+  let mut targets = Vec::new();
+  for member in &workspace.members {
+    for member_dir in expand_member_dirs(member) {
+      let member_manifest_path = match find_member_cargo_toml(&member_dir) {
+        Ok(path) => path,
+        Err(_) => {
+          warn!("skipping workspace member '{}': no Cargo.toml found", member_dir.display());
+          continue;
+        }
+      };
+      let member_manifest = Manifest::from_path(&member_manifest_path)?;
+      targets.push(CrateTarget {
+        name: get_package_name_from_manifest(&member_manifest)?,
+        dir: member_dir,
+      });
+    }
+  }
+
+  Ok(targets)
 }
 
 pub fn get_package_name(cli: bool) -> Result<String, Box<dyn Error>> {
@@ -438,24 +838,99 @@ pub fn get_package_name(cli: bool) -> Result<String, Box<dyn Error>> {
   }
 }
 
-pub fn generate(cli: bool) -> Result<FinchOutput, Box<dyn Error>> {
-  let name = get_package_name(cli)?;
+// Resolves the crates `generate` should run over, and the directory each
+// one's cbindgen + clang pass should run from: the single crate named by
+// `CARGO_PKG_NAME`/`Cargo.toml`'s `[package]` (rooted at the current
+// directory), or, for a workspace manifest with no top-level `[package]`,
+// every member crate rooted at its own member directory.
+fn get_crate_targets(cli: bool) -> Result<Vec<CrateTarget>, Box<dyn Error>> {
+  if !cli {
+    if let Ok(name) = std::env::var("CARGO_PKG_NAME") {
+      return Ok(vec![CrateTarget { name, dir: std::env::current_dir()? }]);
+    }
+  }
+
+  let manifest = Manifest::from_path("Cargo.toml")?;
+  if manifest.package.is_some() {
+    return Ok(vec![CrateTarget {
+      name: get_package_name_from_manifest(&manifest)?,
+      dir: std::env::current_dir()?,
+    }]);
+  }
+
+  get_workspace_member_targets(&manifest)
+}
+
+pub fn get_package_names(cli: bool) -> Result<Vec<String>, Box<dyn Error>> {
+  Ok(get_crate_targets(cli)?.into_iter().map(|target| target.name).collect())
+}
+
+// Links every `FinchType` that is an opaque pointer to a known class back
+// to that `FinchClass` by name, so generators can pass wrapped objects
+// between methods instead of treating them as opaque pointers.
+fn resolve_cross_references(output: &mut FinchOutput) {
+  let classes_by_c_name: HashMap<String, String> = output.classes.values()
+    .map(|class| (class.c_name.clone(), class.name.clone()))
+    .collect();
+
+  let mut resolve = |ty: &mut FinchType| ty.resolve_finch_class(&classes_by_c_name);
+
+  for class in output.classes.values_mut() {
+    // `bases` was captured as each base specifier's raw clang display name
+    // (the same shape as a `FinchClass::c_name`), so it carries a foreign
+    // base through untouched and only rewrites the ones we can resolve.
+    for base in &mut class.bases {
+      if let Some(resolved) = classes_by_c_name.get(base) {
+        *base = resolved.clone();
+      }
+    }
+
+    if let Some(new) = &mut class.new {
+      new.arg_types.iter_mut().for_each(&mut resolve);
+    }
+    for method in &mut class.methods {
+      resolve(&mut method.ret_type);
+      method.arg_types.iter_mut().for_each(&mut resolve);
+    }
+    for static_method in &mut class.statics {
+      resolve(&mut static_method.ret_type);
+      static_method.arg_types.iter_mut().for_each(&mut resolve);
+    }
+    for getter in &mut class.getters {
+      resolve(&mut getter.type_);
+    }
+    for setter in &mut class.setters {
+      resolve(&mut setter.type_);
+    }
+  }
+
+  for function in output.functions.values_mut() {
+    resolve(&mut function.ret_type);
+    function.arg_types.iter_mut().for_each(&mut resolve);
+  }
+
+  for finch_const in output.consts.values_mut() {
+    resolve(&mut finch_const.type_);
+  }
+}
+
+fn generate_for_crate(name: &str, crate_dir: &Path) -> Result<FinchOutput, Box<dyn Error>> {
   let name_underscore = name.replace("-", "_");
 
   let header_name = format!("{}-finch_bindgen.h", name_underscore);
 
   cbindgen::Builder::new()
     .with_namespaces(&vec!["finch", "bindgen", &name_underscore])
-    .with_parse_expand(&vec![name])
+    .with_parse_expand(&vec![name.to_string()])
     .with_parse_deps(true)
     .with_parse_include(&vec!["finch-gen"])
-    .with_crate(std::env::current_dir().unwrap())
+    .with_crate(crate_dir)
     .generate()?.write_to_file(&header_name);
 
   let clang = Clang::new().unwrap();
 
   let index = Index::new(&clang, false, false);
-  
+
   let args = vec!["-std=c++11"];
   let tu = index.parser(header_name).arguments(&args).parse().unwrap();
   let entity = tu.get_entity();
@@ -465,13 +940,39 @@ pub fn generate(cli: bool) -> Result<FinchOutput, Box<dyn Error>> {
     in_internal: false,
     namespace: None,
     classes: HashMap::new(),
+    enums: HashMap::new(),
+    consts: HashMap::new(),
+    functions: HashMap::new(),
+    diagnostics: Vec::new(),
   };
 
-  process_entity(&mut state, entity);
+  if let Err(diagnostic) = process_entity(&mut state, entity) {
+    state.diagnostics.push(diagnostic);
+  }
 
-  Ok(FinchOutput {
+  let mut output = FinchOutput {
     classes: state.classes,
-  })
+    enums: state.enums,
+    consts: state.consts,
+    functions: state.functions,
+    diagnostics: state.diagnostics,
+  };
+
+  resolve_cross_references(&mut output);
+
+  Ok(output)
+}
+
+pub fn generate(cli: bool) -> Result<HashMap<String, FinchOutput>, Box<dyn Error>> {
+  let targets = get_crate_targets(cli)?;
+
+  let mut outputs = HashMap::new();
+  for target in targets {
+    let output = generate_for_crate(&target.name, &target.dir)?;
+    outputs.insert(target.name, output);
+  }
+
+  Ok(outputs)
 }
 
 fn uppercase_first(s: &str) -> String {